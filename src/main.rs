@@ -6,14 +6,23 @@ use axum::{
     routing::{get, post},
     Router,
 };
+use chrono::Utc;
 use dotenv::dotenv;
-use headless_chrome::{types::PrintToPdfOptions, Browser, LaunchOptions};
+use epub_builder::{EpubBuilder, EpubContent, ZipLibrary};
+use futures::stream::{self, StreamExt};
+use headless_chrome::{
+    protocol::cdp::{Emulation, Page},
+    Browser, LaunchOptions,
+};
 use http_req::{request::Method, request::Request, uri::Uri};
-use pdfium_render::prelude::*;
+use kuchiki::traits::TendrilSink;
+use kuchiki::NodeRef;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use std::env;
+use std::ffi::OsString;
 use std::net::SocketAddr;
+use std::time::Duration;
 use std::{fmt, str::FromStr};
 use url::Url;
 
@@ -23,7 +32,9 @@ async fn main() {
     let addr = SocketAddr::from(([10, 0, 0, 15], 4000));
     let app = Router::new()
         .route("/", get(handler))
-        .route("/api", post(handle_post));
+        .route("/api", post(handle_post))
+        .route("/epub", post(handle_epub))
+        .route("/screenshot", post(handle_screenshot));
 
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
@@ -34,6 +45,147 @@ async fn main() {
 #[derive(Debug, Serialize, Deserialize)]
 struct Data {
     url: String,
+    extra_chrome_args: Option<Vec<String>>,
+    user_agent: Option<String>,
+    proxy: Option<String>,
+    window_size: Option<(u32, u32)>,
+    wait_ms: Option<u64>,
+    publish: Option<PublishTarget>,
+}
+
+/// Where to cross-post a generated summary once it's ready. The token
+/// itself is never sent in the request body — `access_token_env` names
+/// the environment variable holding it, same as `OPENAI_API_TOKEN`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PublishTarget {
+    instance_url: String,
+    access_token_env: String,
+    #[serde(default = "default_visibility")]
+    visibility: String,
+}
+
+fn default_visibility() -> String {
+    "public".to_string()
+}
+
+/// Per-request overrides for the headless Chrome session. `/api` and
+/// `/screenshot` take them from the request body so flags/UA/proxy/size
+/// apply the same way on both; `/epub` always launches with the defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct BrowserOptions {
+    extra_chrome_args: Option<Vec<String>>,
+    user_agent: Option<String>,
+    proxy: Option<String>,
+    window_size: Option<(u32, u32)>,
+    wait_ms: Option<u64>,
+}
+
+impl From<&Data> for BrowserOptions {
+    fn from(data: &Data) -> Self {
+        BrowserOptions {
+            extra_chrome_args: data.extra_chrome_args.clone(),
+            user_agent: data.user_agent.clone(),
+            proxy: data.proxy.clone(),
+            window_size: data.window_size,
+            wait_ms: data.wait_ms,
+        }
+    }
+}
+
+/// Chrome flags we're willing to pass through from a request body.
+/// Anything else (e.g. `--remote-debugging-port`) is rejected outright.
+const ALLOWED_CHROME_ARGS: &[&str] = &[
+    "--no-sandbox",
+    "--disable-gpu",
+    "--disable-dev-shm-usage",
+    "--disable-extensions",
+    "--disable-setuid-sandbox",
+    "--lang",
+];
+
+fn validate_chrome_args(args: &[String]) -> anyhow::Result<()> {
+    for arg in args {
+        let name = arg.split('=').next().unwrap_or(arg);
+        if !ALLOWED_CHROME_ARGS.contains(&name) {
+            anyhow::bail!("unsupported or disallowed chrome flag: {arg}");
+        }
+    }
+    Ok(())
+}
+
+/// Launches a headless Chrome tab honoring the given [`BrowserOptions`]:
+/// extra args (validated against an allowlist), an outbound proxy, a
+/// custom user-agent, and a custom window size. Every route that needs
+/// a tab should go through here so options apply uniformly.
+fn launch_tab(opts: &BrowserOptions) -> anyhow::Result<(Browser, std::sync::Arc<headless_chrome::Tab>)> {
+    let mut os_args: Vec<OsString> = Vec::new();
+    if let Some(extra) = &opts.extra_chrome_args {
+        validate_chrome_args(extra)?;
+        os_args.extend(extra.iter().map(OsString::from));
+    }
+    if let Some(proxy) = &opts.proxy {
+        os_args.push(OsString::from(format!("--proxy-server={proxy}")));
+    }
+    let args: Vec<&std::ffi::OsStr> = os_args.iter().map(|s| s.as_os_str()).collect();
+
+    let options = LaunchOptions {
+        headless: true,
+        window_size: Some(opts.window_size.unwrap_or((820, 1180))),
+        args,
+        ..Default::default()
+    };
+
+    let browser = Browser::new(options)?;
+    let tab = browser.new_tab()?;
+
+    if let Some(user_agent) = &opts.user_agent {
+        tab.set_user_agent(user_agent, None, None)?;
+    }
+
+    Ok((browser, tab))
+}
+
+/// Defensive limits on the fetch/extract path, read from the
+/// environment once per request so a single huge or malicious page
+/// can't exhaust memory or blow the GPT token budget.
+///
+/// `max_body_bytes` is enforced twice: [`reject_if_oversized`] rejects
+/// the fetch up front, before a tab is even launched, when the server's
+/// `Content-Length` already exceeds the limit; and `get_text_readability`
+/// truncates the rendered HTML before parsing as a backstop for bodies
+/// that don't declare a length (chunked transfer) or that grow past
+/// their declared size once Chrome renders them. `headless_chrome`'s
+/// `tab.get_content()` exposes no chunked/streaming read, so that
+/// backstop case still materializes the full page in memory for a
+/// moment — the pre-flight check is what closes the common case.
+#[derive(Debug, Clone)]
+struct Config {
+    max_body_bytes: usize,
+    max_title_chars: usize,
+    navigation_timeout_ms: u64,
+}
+
+impl Config {
+    fn from_env() -> Self {
+        Config {
+            max_body_bytes: env_or("MAX_BODY_BYTES", 5 * 1024 * 1024),
+            max_title_chars: env_or("MAX_TITLE_CHARS", 300),
+            navigation_timeout_ms: env_or("NAVIGATION_TIMEOUT_MS", 30_000) as u64,
+        }
+    }
+}
+
+/// Truncates `s` to at most `max_bytes` bytes without splitting a
+/// multi-byte UTF-8 character.
+fn safe_truncate(s: &str, max_bytes: usize) -> String {
+    if s.len() <= max_bytes {
+        return s.to_string();
+    }
+    let mut end = max_bytes;
+    while end > 0 && !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    s[..end].to_string()
 }
 
 async fn handle_post(data: Json<Data>) -> impl IntoResponse {
@@ -45,8 +197,10 @@ async fn handle_post(data: Json<Data>) -> impl IntoResponse {
             .body("parse target url failure".to_string())
             .unwrap();
     } else {
-        match get_text_headless(&data.url).await {
-            Ok(res) => match get_summary_private(res).await {
+        let opts = BrowserOptions::from(&data.0);
+        let config = Config::from_env();
+        match get_text_headless(&data.url, &opts, &config).await {
+            Ok(article) => match get_summary_private(article.text).await {
                 None => {
                     return Response::builder()
                         .status(StatusCode::OK)
@@ -55,22 +209,272 @@ async fn handle_post(data: Json<Data>) -> impl IntoResponse {
                 }
 
                 Some(summary) => {
+                    let mut body = serde_json::json!({
+                        "summary": summary.text,
+                        "truncated": article.truncated,
+                        "dropped_sections": summary.dropped_chunks,
+                    });
+
+                    if let Some(target) = &data.publish {
+                        match post_to_mastodon(&summary.text, &data.url, target).await {
+                            Ok(status_url) => body["toot_url"] = Value::String(status_url),
+                            Err(e) => body["publish_error"] = Value::String(e.to_string()),
+                        }
+                    }
+
                     return Response::builder()
                         .status(StatusCode::OK)
-                        .body(summary)
-                        .unwrap()
+                        .body(body.to_string())
+                        .unwrap();
                 }
             },
-            Err(_) => {
+            Err(e) => {
                 return Response::builder()
                     .status(StatusCode::OK)
-                    .body("failed to get text from webpage".to_string())
+                    .body(format!("failed to get text from webpage: {e}"))
                     .unwrap();
             }
         }
     }
 }
 
+/// Body for `POST /epub`: either a single `url` or a `urls` list (or
+/// both), with an optional `merged` title for the resulting book.
+#[derive(Debug, Deserialize)]
+struct EpubRequest {
+    url: Option<String>,
+    urls: Option<Vec<String>>,
+    merged: Option<String>,
+}
+
+async fn handle_epub(Json(req): Json<EpubRequest>) -> impl IntoResponse {
+    let mut urls = req.urls.unwrap_or_default();
+    if let Some(url) = req.url {
+        urls.push(url);
+    }
+
+    if urls.is_empty() {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(axum::body::Full::from("no url provided"))
+            .unwrap();
+    }
+
+    let config = Config::from_env();
+    let mut articles = Vec::new();
+    let mut errors = Vec::new();
+    for url in urls {
+        match get_text_headless(&url, &BrowserOptions::default(), &config).await {
+            Ok(article) => articles.push((url, article, Utc::now().to_rfc3339())),
+            Err(e) => errors.push(format!("{url}: {e}")),
+        }
+    }
+
+    if articles.is_empty() {
+        return Response::builder()
+            .status(StatusCode::BAD_GATEWAY)
+            .body(axum::body::Full::from(format!(
+                "failed to fetch any source: {}",
+                errors.join("; ")
+            )))
+            .unwrap();
+    }
+    if !errors.is_empty() {
+        println!(
+            "epub: {} of {} source(s) failed: {}",
+            errors.len(),
+            articles.len() + errors.len(),
+            errors.join("; ")
+        );
+    }
+
+    match generate_epub(articles, req.merged) {
+        Ok(bytes) => Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", "application/epub+zip")
+            .body(axum::body::Full::from(bytes))
+            .unwrap(),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(axum::body::Full::from(format!(
+                "failed to build epub: {e}"
+            )))
+            .unwrap(),
+    }
+}
+
+/// Bundles one or more scraped articles into a single EPUB and returns
+/// the raw bytes, ready to stream back as `application/epub+zip`.
+///
+/// Each `(source_url, Article, fetched_at)` triple becomes its own
+/// complete XHTML document chapter, with the article title reinserted
+/// as a visible `<h1>` and its source URL/fetch date recorded both as
+/// visible text and as per-chapter `<meta>` tags (`epub-builder` only
+/// exposes Dublin Core metadata at the book level, so per-article
+/// metadata has to live in each chapter's own `<head>`). `merged_title`
+/// names the book when given; otherwise the first article's title is
+/// used.
+fn generate_epub(
+    articles: Vec<(String, Article, String)>,
+    merged_title: Option<String>,
+) -> Result<Vec<u8>> {
+    let mut builder = EpubBuilder::new(ZipLibrary::new()?)?;
+    builder.inline_toc();
+
+    let title = merged_title.unwrap_or_else(|| {
+        articles
+            .first()
+            .map(|(_, a, _)| a.title.clone())
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| "Scraped Articles".to_string())
+    });
+    builder.metadata("title", &title)?;
+
+    for (i, (url, article, fetched_at)) in articles.iter().enumerate() {
+        let chapter_title = if article.title.is_empty() {
+            format!("Article {}", i + 1)
+        } else {
+            article.title.clone()
+        };
+        let body = html_escape(&article.text)
+            .split("\n\n")
+            .map(|p| format!("<p>{p}</p>"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let heading = html_escape(&chapter_title);
+        let escaped_url = html_escape(url);
+        let xhtml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<!DOCTYPE html>\n\
+<html xmlns=\"http://www.w3.org/1999/xhtml\">\n\
+<head>\n\
+<title>{heading}</title>\n\
+<meta name=\"dc:source\" content=\"{escaped_url}\"/>\n\
+<meta name=\"dc:date\" content=\"{fetched_at}\"/>\n\
+</head>\n\
+<body>\n\
+<h1>{heading}</h1>\n\
+<p><em>Source: {escaped_url} — fetched {fetched_at}</em></p>\n\
+{body}\n\
+</body>\n\
+</html>"
+        );
+        builder.add_content(
+            EpubContent::new(format!("chapter_{}.xhtml", i + 1), xhtml.as_bytes())
+                .title(chapter_title),
+        )?;
+    }
+
+    let mut bytes = Vec::new();
+    builder.generate(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Body for `POST /screenshot`. `browser` is flattened so the same
+/// extra-args/UA/proxy/window-size/wait overrides as `/api` apply here.
+#[derive(Debug, Deserialize)]
+struct ScreenshotRequest {
+    url: String,
+    #[serde(default)]
+    full_page: bool,
+    #[serde(default = "default_screenshot_format")]
+    format: String,
+    quality: Option<u8>,
+    #[serde(flatten)]
+    browser: BrowserOptions,
+}
+
+fn default_screenshot_format() -> String {
+    "png".to_string()
+}
+
+async fn handle_screenshot(data: Json<ScreenshotRequest>) -> impl IntoResponse {
+    if Url::from_str(&data.url).is_err() {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(axum::body::Full::from("parse target url failure"))
+            .unwrap();
+    }
+
+    let config = Config::from_env();
+    match capture_screenshot(&data, &config).await {
+        Ok((bytes, content_type)) => Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", content_type)
+            .body(axum::body::Full::from(bytes))
+            .unwrap(),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(axum::body::Full::from(format!(
+                "failed to capture screenshot: {e}"
+            )))
+            .unwrap(),
+    }
+}
+
+/// Captures a PNG/JPEG of `req.url` via the shared [`launch_tab`] helper.
+/// For `full_page`, first queries `Page.getLayoutMetrics`, then actually
+/// resizes the viewport to the full content height via
+/// `Emulation.setDeviceMetricsOverride` before capturing — just clipping
+/// to the content size without resizing would still only render
+/// whatever fit in the original window.
+async fn capture_screenshot(
+    req: &ScreenshotRequest,
+    config: &Config,
+) -> anyhow::Result<(Vec<u8>, &'static str)> {
+    let (_browser, tab) = launch_tab(&req.browser)?;
+    navigate_with_timeout(
+        tab.clone(),
+        req.url.clone(),
+        req.browser.wait_ms,
+        config.navigation_timeout_ms,
+    )
+    .await?;
+
+    let is_jpeg = req.format.eq_ignore_ascii_case("jpeg");
+    let format = if is_jpeg {
+        Page::CaptureScreenshotFormatOption::Jpeg
+    } else {
+        Page::CaptureScreenshotFormatOption::Png
+    };
+
+    let clip = if req.full_page {
+        let metrics = tab.call_method(Page::GetLayoutMetrics {})?;
+        let width = metrics.css_content_size.width;
+        let height = metrics.css_content_size.height;
+
+        tab.call_method(Emulation::SetDeviceMetricsOverride {
+            width: width as u32,
+            height: height as u32,
+            device_scale_factor: 1.0,
+            mobile: false,
+            ..Default::default()
+        })?;
+
+        Some(Page::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+            scale: 1.0,
+        })
+    } else {
+        None
+    };
+
+    let bytes = tab.capture_screenshot(format, req.quality.map(|q| q as u32), clip, true)?;
+
+    Ok((bytes, if is_jpeg { "image/jpeg" } else { "image/png" }))
+}
+
 #[derive(Debug, serde::Serialize)]
 struct MyResponse {
     text: String,
@@ -101,64 +505,241 @@ where
     }
 }
 
-async fn get_text_headless(url: &str) -> anyhow::Result<String> {
-    // set the headless Chrome to open a webpage in portrait mode of certain width and height
-    // here in an iPad resolution, is a way to pursuade webserver to send less non-essential
-    // data, and make the virtual browser to show the central content, for websites
-    // with responsive design, with less clutter
-    let options = LaunchOptions {
-        headless: true,
-        window_size: Some((820, 1180)),
-        ..Default::default()
-    };
+/// Navigates `tab` to `url`, honoring `opts.wait_ms` (a fixed delay for
+/// JS-heavy SPAs) or falling back to `wait_until_navigated`, and bounds
+/// the whole thing by `config.navigation_timeout_ms` so a hung load
+/// fails fast instead of blocking the worker.
+///
+/// `headless_chrome`'s navigation calls are blocking, not async, so the
+/// actual navigation runs on `spawn_blocking`'s dedicated thread pool;
+/// we only `tokio::time::timeout` the *join handle*. A load that's
+/// truly hung leaves its blocking thread running in the background
+/// (there's no way to interrupt it), but the caller gets its error back
+/// on schedule instead of the worker stalling with it.
+async fn navigate_with_timeout(
+    tab: std::sync::Arc<headless_chrome::Tab>,
+    url: String,
+    wait_ms: Option<u64>,
+    navigation_timeout_ms: u64,
+) -> anyhow::Result<()> {
+    let navigation = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+        tab.navigate_to(&url)?;
+        match wait_ms {
+            Some(ms) => std::thread::sleep(Duration::from_millis(ms)),
+            None => {
+                tab.wait_until_navigated()?;
+            }
+        }
+        Ok(())
+    });
 
-    let browser = Browser::new(options)?;
+    match tokio::time::timeout(Duration::from_millis(navigation_timeout_ms), navigation).await {
+        Ok(join_result) => join_result?,
+        Err(_) => anyhow::bail!("navigation timed out after {navigation_timeout_ms}ms"),
+    }
+}
 
-    let tab = browser.new_tab()?;
+/// Issues a `HEAD` request and rejects the fetch outright if the server
+/// advertises a body bigger than `config.max_body_bytes`, so we never
+/// even launch a tab — and thus never have Chrome materialize the page
+/// in memory — for a page we already know is oversized. This is a
+/// best-effort ingestion-time guard, not a complete one: a server that
+/// omits `Content-Length` (e.g. chunked transfer) isn't caught here and
+/// falls through to the pre-parse cap in `get_text_readability`.
+fn reject_if_oversized(url: &str, config: &Config) -> anyhow::Result<()> {
+    let uri = Uri::try_from(url)?;
+    let mut writer = Vec::new();
+    let response = Request::new(&uri).method(Method::HEAD).send(&mut writer)?;
+
+    if let Some(len) = response.content_len() {
+        if len > config.max_body_bytes {
+            anyhow::bail!(
+                "refusing to fetch {url}: advertised body size {len} bytes exceeds max_body_bytes ({})",
+                config.max_body_bytes
+            );
+        }
+    }
+    Ok(())
+}
 
-    tab.navigate_to(url)?;
-    tab.wait_until_navigated();
-
-    let pdf_options: Option<PrintToPdfOptions> = Some(PrintToPdfOptions {
-        landscape: Some(false),
-        display_header_footer: Some(false),
-        print_background: Some(false),
-        scale: Some(0.5),
-        paper_width: Some(11.0),
-        paper_height: Some(17.0),
-        margin_top: Some(0.1),
-        margin_bottom: Some(0.1),
-        margin_left: Some(0.1),
-        margin_right: Some(0.1),
-        page_ranges: Some("1-2".to_string()),
-        ignore_invalid_page_ranges: Some(true),
-        prefer_css_page_size: Some(false),
-        transfer_mode: None,
-        ..Default::default()
-    });
+async fn get_text_headless(
+    url: &str,
+    opts: &BrowserOptions,
+    config: &Config,
+) -> anyhow::Result<Article> {
+    reject_if_oversized(url, config)?;
+
+    // default window size is an iPad resolution in portrait mode, a way to
+    // pursuade webserver to send less non-essential data, and make the
+    // virtual browser show the central content, for websites with
+    // responsive design, with less clutter
+    let (_browser, tab) = launch_tab(opts)?;
+    navigate_with_timeout(
+        tab.clone(),
+        url.to_string(),
+        opts.wait_ms,
+        config.navigation_timeout_ms,
+    )
+    .await?;
+
+    let html = tab.get_content()?;
+
+    get_text_readability(&html, config)
+}
 
-    let pdf_data = tab.print_to_pdf(pdf_options)?;
-
-    let pdf_as_vec = pdf_data.to_vec();
-    //code below uses dynamically linked libpdfium.dylib on a M1 Mac
-    //it takes some efforts to bind libpdfium on different platforms
-    //please visit https://github.com/ajrcarey/pdfium-render/tree/master
-    //for more details
-    let text = Pdfium::new(
-        Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(
-            "/home/ubuntu/pdfium/lib/",
-            // "/Users/jaykchen/Downloads/pdfium-mac-arm64/lib/libpdfium.dylib",
-        ))
-        .or_else(|_| Pdfium::bind_to_system_library())?,
+/// A single extracted article: its title, an optional byline, the
+/// plain-text body of whatever the readability scorer picked as the
+/// main content node, and whether the source page was truncated to fit
+/// `Config::max_body_bytes`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Article {
+    pub title: String,
+    pub byline: Option<String>,
+    pub text: String,
+    pub truncated: bool,
+}
+
+const POSITIVE_CLASS_HINTS: &[&str] = &["article", "content", "post", "body"];
+const NEGATIVE_CLASS_HINTS: &[&str] = &["comment", "nav", "sidebar", "footer", "ad", "menu"];
+
+/// Readability-style main-content extraction, done entirely in-process
+/// against a DOM tree (no headless round-trip through a PDF renderer).
+///
+/// Walks every `<p>`/`<td>`/`<pre>` node, scores it by how much text
+/// (and how many commas) it holds, and hands that score up to its
+/// parent (full weight) and grandparent (half weight) — those ancestors,
+/// not the leaf node itself, are the candidates for "main content".
+/// Candidates get a class/id bonus or penalty, are divided by
+/// (1 − link density), and the highest-scoring one wins. The raw HTML is
+/// capped at `config.max_body_bytes` before parsing — a best-effort,
+/// post-hoc cap (see [`Config`]), not a bound on the memory used to
+/// fetch it in the first place.
+fn get_text_readability(html: &str, config: &Config) -> anyhow::Result<Article> {
+    let truncated = html.len() > config.max_body_bytes;
+    let html = if truncated {
+        safe_truncate(html, config.max_body_bytes)
+    } else {
+        html.to_string()
+    };
+    let document = kuchiki::parse_html().one(html);
+
+    let title = document
+        .select_first("title")
+        .map(|t| t.text_contents().trim().to_string())
+        .unwrap_or_default();
+    let title: String = title.chars().take(config.max_title_chars).collect();
+
+    let byline = document
+        .select("[rel=author], .byline, .author")
+        .ok()
+        .and_then(|mut it| it.next())
+        .map(|n| n.text_contents().trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    let mut scores: Vec<(NodeRef, f64)> = Vec::new();
+
+    if let Ok(matches) = document.select("p, td, pre") {
+        for m in matches {
+            let node = m.as_node();
+            let text = node.text_contents();
+            let comma_bonus = text.matches(',').count() as f64;
+            let length_bonus = (text.len() / 100).min(3) as f64;
+            let content_score = 1.0 + comma_bonus + length_bonus;
+
+            if let Some(parent) = node.parent() {
+                bump_score(&mut scores, &parent, content_score);
+                if let Some(grandparent) = parent.parent() {
+                    bump_score(&mut scores, &grandparent, content_score * 0.5);
+                }
+            }
+        }
+    }
+
+    let top_candidate = scores
+        .into_iter()
+        .map(|(node, score)| {
+            let weighted = score + class_id_weight(&node);
+            let density = link_density(&node);
+            (node, weighted / (1.0 - density).max(0.05))
+        })
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(node, _)| node)
+        .unwrap_or_else(|| document.clone());
+
+    let text = serialize_visible_text(&top_candidate);
+
+    Ok(Article {
+        title,
+        byline,
+        text,
+        truncated,
+    })
+}
+
+/// Adds `amount` to `node`'s running score, creating an entry if this is
+/// the first time `node` has been seen as a candidate.
+fn bump_score(scores: &mut Vec<(NodeRef, f64)>, node: &NodeRef, amount: f64) {
+    if let Some((_, score)) = scores.iter_mut().find(|(n, _)| n == node) {
+        *score += amount;
+    } else {
+        scores.push((node.clone(), amount));
+    }
+}
+
+/// +25 for an `article|content|post|body` class/id, -25 for a
+/// `comment|nav|sidebar|footer|ad|menu` one, 0 otherwise.
+fn class_id_weight(node: &NodeRef) -> f64 {
+    let Some(element) = node.as_element() else {
+        return 0.0;
+    };
+    let attrs = element.attributes.borrow();
+    let haystack = format!(
+        "{} {}",
+        attrs.get("class").unwrap_or(""),
+        attrs.get("id").unwrap_or("")
     )
-    .load_pdf_from_byte_vec(pdf_as_vec, Some(""))?
-    .pages()
-    .iter()
-    .map(|page| page.text().unwrap().all())
-    .collect::<Vec<String>>()
-    .join(" ");
+    .to_ascii_lowercase();
+
+    let mut weight = 0.0;
+    if POSITIVE_CLASS_HINTS.iter().any(|p| haystack.contains(p)) {
+        weight += 25.0;
+    }
+    if NEGATIVE_CLASS_HINTS.iter().any(|p| haystack.contains(p)) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+/// Fraction of `node`'s text that sits inside `<a>` anchors.
+fn link_density(node: &NodeRef) -> f64 {
+    let total_len = node.text_contents().len();
+    if total_len == 0 {
+        return 0.0;
+    }
+    let link_len: usize = node
+        .select("a")
+        .map(|matches| matches.map(|a| a.text_contents().len()).sum())
+        .unwrap_or(0);
+    link_len as f64 / total_len as f64
+}
 
-    Ok(text)
+/// Serializes the visible text of the winning candidate, keeping
+/// `<h1>`/`<p>`/`<li>` nodes as separate paragraphs.
+fn serialize_visible_text(node: &NodeRef) -> String {
+    let mut parts = Vec::new();
+    if let Ok(matches) = node.select("h1, p, li") {
+        for m in matches {
+            let text = m.text_contents().trim().to_string();
+            if !text.is_empty() {
+                parts.push(text);
+            }
+        }
+    }
+    if parts.is_empty() {
+        node.text_contents().trim().to_string()
+    } else {
+        parts.join("\n\n")
+    }
 }
 
 pub async fn custom_gpt(sys_prompt: &str, u_prompt: &str, m_token: u16) -> Option<String> {
@@ -234,14 +815,175 @@ pub struct Message {
     pub content: String,
 }
 
-async fn get_summary_private(inp: String) -> Option<String> {
-    let mut feed_texts = inp.split_ascii_whitespace().collect::<Vec<&str>>();
-    feed_texts.truncate(3000);
+const MASTODON_STATUS_CHAR_LIMIT: usize = 500;
+
+/// Posts `summary — source_url` as a new status to `target`'s instance,
+/// trimming the summary (never the link) so the whole status fits in
+/// [`MASTODON_STATUS_CHAR_LIMIT`] characters, and returns the created
+/// status's URL. The instance's own configured limit is not queried.
+async fn post_to_mastodon(
+    summary: &str,
+    source_url: &str,
+    target: &PublishTarget,
+) -> anyhow::Result<String> {
+    let access_token = env::var(&target.access_token_env)?;
+
+    let suffix = format!(" — {source_url}");
+    let max_summary_chars = MASTODON_STATUS_CHAR_LIMIT.saturating_sub(suffix.chars().count());
+    let trimmed_summary: String = summary.chars().take(max_summary_chars).collect();
+    let status = format!("{trimmed_summary}{suffix}");
+
+    let params = serde_json::json!({
+        "status": status,
+        "visibility": target.visibility,
+    });
+
+    let uri = format!(
+        "{}/api/v1/statuses",
+        target.instance_url.trim_end_matches('/')
+    );
+    let uri = Uri::try_from(uri.as_str())?;
+    let body = serde_json::to_vec(&params)?;
+    let bearer_token = format!("Bearer {access_token}");
+
+    let mut writer = Vec::new();
+    Request::new(&uri)
+        .method(Method::POST)
+        .header("Authorization", &bearer_token)
+        .header("Content-Type", "application/json")
+        .header("Content-Length", &body.len())
+        .body(&body)
+        .send(&mut writer)?;
 
-    let news_body = feed_texts.join(" ");
+    let status: MastodonStatus = serde_json::from_slice(&writer)?;
+    Ok(status.url)
+}
+
+#[derive(Deserialize)]
+struct MastodonStatus {
+    url: String,
+}
+
+// ~4 chars/token is a rough-but-good-enough estimate for English text,
+// and lets us budget chunks without pulling in a real tokenizer.
+const CHARS_PER_TOKEN: usize = 4;
+const DEFAULT_CHUNK_TOKENS: usize = 2500;
+const DEFAULT_CHUNK_OVERLAP_TOKENS: usize = 100;
+const DEFAULT_MAP_MAX_TOKENS: u16 = 256;
+const DEFAULT_REDUCE_MAX_TOKENS: u16 = 512;
+const MAP_CONCURRENCY: usize = 4;
+
+fn env_or(key: &str, default: usize) -> usize {
+    env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// Splits whitespace-tokenized `text` into chunks of roughly
+/// `chunk_tokens` tokens (estimated at [`CHARS_PER_TOKEN`] chars/token),
+/// with a small word-level overlap between consecutive chunks so
+/// context isn't lost at a cut point.
+fn split_into_chunks(text: &str, chunk_tokens: usize, overlap_tokens: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_ascii_whitespace().collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    let chunk_chars = chunk_tokens * CHARS_PER_TOKEN;
+    let overlap_chars = overlap_tokens * CHARS_PER_TOKEN;
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let mut end = start;
+        let mut len = 0;
+        while end < words.len() && (len < chunk_chars || end == start) {
+            len += words[end].len() + 1;
+            end += 1;
+        }
+        chunks.push(words[start..end].join(" "));
+        if end >= words.len() {
+            break;
+        }
+
+        let mut back = end;
+        let mut overlap_len = 0;
+        while back > start && overlap_len < overlap_chars {
+            back -= 1;
+            overlap_len += words[back].len() + 1;
+        }
+        start = back.max(start + 1);
+    }
+    chunks
+}
+
+fn summary_user_prompt(news_body: &str) -> String {
+    format!("Given the news body text: {news_body}, which may include some irrelevant information, identify the key arguments and the article's conclusion. From these important elements, construct a succinct summary that encapsulates its news value, disregarding any unnecessary details.")
+}
+
+/// A map-reduce summary, plus how many mapped chunks (if any) failed
+/// their `custom_gpt` call and were dropped from the merge — so a
+/// partial failure is visible to the caller instead of silently
+/// shrinking the summary.
+#[derive(Debug, Clone)]
+struct Summary {
+    text: String,
+    dropped_chunks: usize,
+}
+
+/// Map-reduce summarization: the article is split into token-budgeted
+/// chunks, each is summarized independently (the *map* step, run
+/// concurrently), and the partial summaries are merged by one final
+/// call (the *reduce* step). A document that already fits in one chunk
+/// skips straight to a single summarization call.
+async fn get_summary_private(inp: String) -> Option<Summary> {
+    let chunk_tokens = env_or("SUMMARY_CHUNK_TOKENS", DEFAULT_CHUNK_TOKENS);
+    let overlap_tokens = env_or("SUMMARY_CHUNK_OVERLAP_TOKENS", DEFAULT_CHUNK_OVERLAP_TOKENS);
+    let map_max_tokens = env_or("SUMMARY_MAP_MAX_TOKENS", DEFAULT_MAP_MAX_TOKENS as usize) as u16;
+    let reduce_max_tokens =
+        env_or("SUMMARY_REDUCE_MAX_TOKENS", DEFAULT_REDUCE_MAX_TOKENS as usize) as u16;
 
     let sys_prompt = "You're a new reporter AI.";
-    let user_prompt = &format!("Given the news body text: {news_body}, which may include some irrelevant information, identify the key arguments and the article's conclusion. From these important elements, construct a succinct summary that encapsulates its news value, disregarding any unnecessary details.");
+    let mut chunks = split_into_chunks(&inp, chunk_tokens, overlap_tokens);
+
+    if chunks.len() <= 1 {
+        let news_body = chunks.pop().unwrap_or(inp);
+        let text = custom_gpt(sys_prompt, &summary_user_prompt(&news_body), reduce_max_tokens).await?;
+        return Some(Summary {
+            text,
+            dropped_chunks: 0,
+        });
+    }
+
+    // Tag each chunk with its original position so the map step can run
+    // out of order (buffer_unordered) while the reduce step still reads
+    // the sections in document order.
+    let mut indexed_summaries: Vec<(usize, Option<String>)> =
+        stream::iter(chunks.into_iter().enumerate().map(|(i, chunk)| async move {
+            let summary = custom_gpt(
+                "You're a news reporter AI summarizing one section of a longer article.",
+                &format!("Summarize the key points of this section of a larger article:\n\n{chunk}"),
+                map_max_tokens,
+            )
+            .await;
+            (i, summary)
+        }))
+        .buffer_unordered(MAP_CONCURRENCY)
+        .collect()
+        .await;
+    indexed_summaries.sort_by_key(|(i, _)| *i);
+
+    let dropped_chunks = indexed_summaries.iter().filter(|(_, s)| s.is_none()).count();
+    let partial_summaries: Vec<String> = indexed_summaries.into_iter().filter_map(|(_, s)| s).collect();
+
+    if partial_summaries.is_empty() {
+        return None;
+    }
+
+    let merged = partial_summaries.join("\n\n");
+    let reduce_prompt = format!("Here are summaries of consecutive sections of one article:\n\n{merged}\n\nMerge these into one coherent, succinct summary that captures the article's key arguments and conclusion, disregarding any redundancy between sections.");
 
-    custom_gpt(sys_prompt, user_prompt, 512).await
+    let text = custom_gpt(sys_prompt, &reduce_prompt, reduce_max_tokens).await?;
+    Some(Summary {
+        text,
+        dropped_chunks,
+    })
 }